@@ -2,17 +2,54 @@ use algebra::Field;
 use std::hash::Hash;
 
 use crate::crh::{
+    FixedLengthCRH,
     FixedLengthCRHGadget,
     pedersen::PedersenWindow,
     bowe_hopwood::{BoweHopwoodPedersenCRH, BoweHopwoodPedersenParameters, CHUNK_SIZE},
 };
 use algebra::groups::Group;
+use algebra::ProjectiveCurve;
 use r1cs_core::{ConstraintSystem, SynthesisError};
-use r1cs_std::{groups::GroupGadget, uint8::UInt8, alloc::AllocGadget};
+use r1cs_std::{
+    bits::ToBitsGadget, eq::EqGadget, fields::fp::FpGadget, groups::GroupGadget, select::CondSelectGadget,
+    uint8::UInt8, alloc::AllocGadget,
+};
 
 use std::{borrow::Borrow, marker::PhantomData};
 use r1cs_std::bits::boolean::Boolean;
 
+/// A group gadget whose affine x-coordinate can be extracted as a standalone
+/// field element, so it can serve as a compressed CRH output.
+pub trait CompressedGroupGadget<ConstraintF: Field> {
+    fn x_coordinate(&self) -> FpGadget<ConstraintF>;
+}
+
+impl<G: ProjectiveCurve, W: PedersenWindow> BoweHopwoodPedersenCRH<G, W> {
+    /// Like `evaluate`, but returns only the affine x-coordinate of the
+    /// resulting point, matching `BoweHopwoodPedersenCompressedCRHGadget`'s
+    /// compressed, single-field-element output.
+    pub fn evaluate_compressed(
+        parameters: &BoweHopwoodPedersenParameters<G>,
+        input: &[u8],
+    ) -> Result<<G::Affine as algebra::AffineCurve>::BaseField, <Self as FixedLengthCRH>::Error> {
+        let result = <Self as FixedLengthCRH>::evaluate(parameters, input)?;
+        Ok(result.into_affine().x)
+    }
+
+    /// Like `evaluate`, but prepends a fixed `personalization` domain tag to
+    /// the input before hashing, matching
+    /// `check_evaluation_gadget_with_personalization`.
+    pub fn evaluate_with_personalization(
+        parameters: &BoweHopwoodPedersenParameters<G>,
+        personalization: &[u8],
+        input: &[u8],
+    ) -> Result<G, <Self as FixedLengthCRH>::Error> {
+        let mut data = personalization.to_vec();
+        data.extend_from_slice(input);
+        <Self as FixedLengthCRH>::evaluate(parameters, &data)
+    }
+}
+
 #[derive(Derivative)]
 #[derivative(Clone(
     bound = "G: Group, W: PedersenWindow, ConstraintF: Field, GG: GroupGadget<G, ConstraintF>"
@@ -50,10 +87,31 @@ where
         parameters: &Self::ParametersGadget,
         input: &[UInt8],
     ) -> Result<Self::OutputGadget, SynthesisError> {
+        Self::check_evaluation_gadget_with_personalization(cs, parameters, &[], input)
+    }
+}
+
+impl<ConstraintF, G, GG, W> BoweHopwoodPedersenCRHGadget<G, ConstraintF, GG>
+where
+    ConstraintF: Field,
+    G: Group + Hash,
+    GG: GroupGadget<G, ConstraintF>,
+    W: PedersenWindow,
+{
+    /// Like `check_evaluation_gadget`, but prepends a fixed `personalization`
+    /// domain tag (known at circuit-synthesis time) to the input before
+    /// windowing it, so hashes used for different purposes don't collide.
+    pub fn check_evaluation_gadget_with_personalization<CS: ConstraintSystem<ConstraintF>>(
+        cs: CS,
+        parameters: &BoweHopwoodPedersenCRHGadgetParameters<G, W, ConstraintF, GG>,
+        personalization: &[u8],
+        input: &[UInt8],
+    ) -> Result<GG, SynthesisError> {
         // Pad the input if it is not the current length.
-        let mut input_in_bits: Vec<_> = input
+        let mut input_in_bits: Vec<_> = personalization
             .iter()
-            .flat_map(|byte| byte.into_bits_le())
+            .flat_map(|byte| (0..8).map(move |i| Boolean::constant((byte >> i) & 1 == 1)))
+            .chain(input.iter().flat_map(|byte| byte.into_bits_le()))
             .collect();
         if (input_in_bits.len()) % CHUNK_SIZE != 0 {
             let current_length = input_in_bits.len();
@@ -64,6 +122,13 @@ where
         assert!(input_in_bits.len() % CHUNK_SIZE == 0);
         assert_eq!(parameters.params.generators.len(), W::NUM_WINDOWS*W::WINDOW_SIZE);
 
+        if input_in_bits.len() > W::NUM_WINDOWS * W::WINDOW_SIZE * CHUNK_SIZE {
+            // The generator table is too small for this input; this is a
+            // malformed-assignment error, not a genuinely unsatisfiable
+            // constraint system, so don't conflate it with `Unsatisfiable`.
+            return Err(SynthesisError::AssignmentMissing);
+        }
+
         // Allocate new variable for the result.
 
         let input_in_bits = input_in_bits.chunks(CHUNK_SIZE);
@@ -74,6 +139,118 @@ where
     }
 }
 
+pub struct BoweHopwoodPedersenCompressedCRHGadget<G: Group, ConstraintF: Field, GG: GroupGadget<G, ConstraintF>> {
+    _group:        PhantomData<*const G>,
+    _group_gadget: PhantomData<*const GG>,
+    _engine:       PhantomData<ConstraintF>,
+}
+
+impl<ConstraintF, G, GG, W> FixedLengthCRHGadget<BoweHopwoodPedersenCRH<G, W>, ConstraintF> for BoweHopwoodPedersenCompressedCRHGadget<G, ConstraintF, GG>
+where
+    ConstraintF: Field,
+    G: Group + Hash,
+    GG: GroupGadget<G, ConstraintF> + CompressedGroupGadget<ConstraintF>,
+    W: PedersenWindow,
+{
+    type OutputGadget = FpGadget<ConstraintF>;
+    type ParametersGadget = BoweHopwoodPedersenCRHGadgetParameters<G, W, ConstraintF, GG>;
+
+    fn check_evaluation_gadget<CS: ConstraintSystem<ConstraintF>>(
+        cs: CS,
+        parameters: &Self::ParametersGadget,
+        input: &[UInt8],
+    ) -> Result<Self::OutputGadget, SynthesisError> {
+        let result =
+            <BoweHopwoodPedersenCRHGadget<G, ConstraintF, GG> as FixedLengthCRHGadget<BoweHopwoodPedersenCRH<G, W>, ConstraintF>>::check_evaluation_gadget(
+                cs, parameters, input,
+            )?;
+
+        Ok(result.x_coordinate())
+    }
+}
+
+pub struct TwoToOneCRHGadget<G: Group, ConstraintF: Field, GG: GroupGadget<G, ConstraintF>> {
+    _group:        PhantomData<*const G>,
+    _group_gadget: PhantomData<*const GG>,
+    _engine:       PhantomData<ConstraintF>,
+}
+
+impl<G, ConstraintF, GG, W> TwoToOneCRHGadget<G, ConstraintF, GG>
+where
+    ConstraintF: Field,
+    G: Group + Hash,
+    GG: GroupGadget<G, ConstraintF>,
+    W: PedersenWindow,
+{
+    pub fn check_evaluation_gadget<CS: ConstraintSystem<ConstraintF>>(
+        mut cs: CS,
+        parameters: &<BoweHopwoodPedersenCRHGadget<G, ConstraintF, GG> as FixedLengthCRHGadget<BoweHopwoodPedersenCRH<G, W>, ConstraintF>>::ParametersGadget,
+        left: &GG,
+        right: &GG,
+    ) -> Result<GG, SynthesisError> {
+        let mut bytes = left.to_bytes(&mut cs.ns(|| "left_to_bytes"))?;
+        bytes.extend(right.to_bytes(&mut cs.ns(|| "right_to_bytes"))?);
+
+        <BoweHopwoodPedersenCRHGadget<G, ConstraintF, GG> as FixedLengthCRHGadget<BoweHopwoodPedersenCRH<G, W>, ConstraintF>>::check_evaluation_gadget(
+            cs.ns(|| "hash"),
+            parameters,
+            &bytes,
+        )
+    }
+}
+
+pub struct MerklePathGadget<G: Group, ConstraintF: Field, GG: GroupGadget<G, ConstraintF>> {
+    path:    Vec<(GG, Boolean)>,
+    _group:  PhantomData<*const G>,
+    _engine: PhantomData<ConstraintF>,
+}
+
+impl<G, ConstraintF, GG> MerklePathGadget<G, ConstraintF, GG>
+where
+    ConstraintF: Field,
+    G: Group + Hash,
+    GG: GroupGadget<G, ConstraintF>,
+{
+    pub fn new(path: Vec<(GG, Boolean)>) -> Self {
+        MerklePathGadget {
+            path,
+            _group: PhantomData,
+            _engine: PhantomData,
+        }
+    }
+
+    pub fn check_membership<CS: ConstraintSystem<ConstraintF>, W: PedersenWindow>(
+        &self,
+        mut cs: CS,
+        parameters: &<BoweHopwoodPedersenCRHGadget<G, ConstraintF, GG> as FixedLengthCRHGadget<BoweHopwoodPedersenCRH<G, W>, ConstraintF>>::ParametersGadget,
+        leaf: &[UInt8],
+        root: &GG,
+    ) -> Result<(), SynthesisError> {
+        let mut current =
+            <BoweHopwoodPedersenCRHGadget<G, ConstraintF, GG> as FixedLengthCRHGadget<BoweHopwoodPedersenCRH<G, W>, ConstraintF>>::check_evaluation_gadget(
+                cs.ns(|| "hash_leaf"),
+                parameters,
+                leaf,
+            )?;
+
+        for (i, (sibling, is_right)) in self.path.iter().enumerate() {
+            let mut cs = cs.ns(|| format!("level_{}", i));
+
+            let left = GG::conditionally_select(cs.ns(|| "select_left"), is_right, sibling, &current)?;
+            let right = GG::conditionally_select(cs.ns(|| "select_right"), is_right, &current, sibling)?;
+
+            current = TwoToOneCRHGadget::<G, ConstraintF, GG>::check_evaluation_gadget(
+                cs.ns(|| "hash_level"),
+                parameters,
+                &left,
+                &right,
+            )?;
+        }
+
+        current.enforce_equal(&mut cs.ns(|| "check_root"), root)
+    }
+}
+
 impl<G: Group, W: PedersenWindow, ConstraintF: Field, GG: GroupGadget<G, ConstraintF>>
     AllocGadget<BoweHopwoodPedersenParameters<G>, ConstraintF> for BoweHopwoodPedersenCRHGadgetParameters<G, W, ConstraintF, GG>
 {
@@ -109,6 +286,181 @@ impl<G: Group, W: PedersenWindow, ConstraintF: Field, GG: GroupGadget<G, Constra
     }
 }
 
+#[derive(Derivative)]
+#[derivative(Clone(bound = "G: Group"))]
+pub struct BoweHopwoodPedersenCommitmentParameters<G: Group> {
+    pub crh_parameters:       BoweHopwoodPedersenParameters<G>,
+    pub randomness_generator: G,
+}
+
+#[derive(Derivative)]
+#[derivative(Clone(
+    bound = "G: Group, W: PedersenWindow, ConstraintF: Field, GG: GroupGadget<G, ConstraintF>"
+))]
+pub struct BoweHopwoodPedersenCommitmentGadgetParameters<
+    G: Group,
+    W: PedersenWindow,
+    ConstraintF: Field,
+    GG: GroupGadget<G, ConstraintF>,
+> {
+    params:   BoweHopwoodPedersenCommitmentParameters<G>,
+    _group_g: PhantomData<GG>,
+    _engine:  PhantomData<ConstraintF>,
+    _window:  PhantomData<W>,
+}
+
+pub struct BoweHopwoodPedersenCommitmentGadget<G: Group, ConstraintF: Field, GG: GroupGadget<G, ConstraintF>> {
+    _group:        PhantomData<*const G>,
+    _group_gadget: PhantomData<*const GG>,
+    _engine:       PhantomData<ConstraintF>,
+}
+
+impl<G, ConstraintF, GG, W> BoweHopwoodPedersenCommitmentGadget<G, ConstraintF, GG>
+where
+    ConstraintF: Field,
+    G: Group + Hash,
+    GG: GroupGadget<G, ConstraintF> + AllocGadget<G, ConstraintF>,
+    W: PedersenWindow,
+{
+    pub fn check_commitment_gadget<CS: ConstraintSystem<ConstraintF>>(
+        mut cs: CS,
+        parameters: &BoweHopwoodPedersenCommitmentGadgetParameters<G, W, ConstraintF, GG>,
+        input: &[UInt8],
+        randomness: &[UInt8],
+    ) -> Result<GG, SynthesisError> {
+        let crh_parameters = BoweHopwoodPedersenCRHGadgetParameters::<G, W, ConstraintF, GG> {
+            params:   parameters.params.crh_parameters.clone(),
+            _group_g: PhantomData,
+            _engine:  PhantomData,
+            _window:  PhantomData,
+        };
+        let message_result =
+            <BoweHopwoodPedersenCRHGadget<G, ConstraintF, GG> as FixedLengthCRHGadget<BoweHopwoodPedersenCRH<G, W>, ConstraintF>>::check_evaluation_gadget(
+                cs.ns(|| "message_commitment"),
+                &crh_parameters,
+                input,
+            )?;
+
+        let randomness_bits: Vec<_> = randomness.iter().flat_map(|byte| byte.into_bits_le()).collect();
+        let randomness_generator =
+            GG::alloc(cs.ns(|| "randomness_generator"), || Ok(parameters.params.randomness_generator))?;
+        let zero = GG::zero(cs.ns(|| "randomness_commitment_zero"))?;
+        let randomness_result =
+            randomness_generator.mul_bits(cs.ns(|| "randomness_commitment"), &zero, randomness_bits.iter())?;
+
+        message_result.add(cs.ns(|| "combine_message_and_randomness"), &randomness_result)
+    }
+}
+
+impl<G: Group, W: PedersenWindow, ConstraintF: Field, GG: GroupGadget<G, ConstraintF>>
+    AllocGadget<BoweHopwoodPedersenCommitmentParameters<G>, ConstraintF> for BoweHopwoodPedersenCommitmentGadgetParameters<G, W, ConstraintF, GG>
+{
+    fn alloc<F, T, CS: ConstraintSystem<ConstraintF>>(_cs: CS, value_gen: F) -> Result<Self, SynthesisError>
+    where
+        F: FnOnce() -> Result<T, SynthesisError>,
+        T: Borrow<BoweHopwoodPedersenCommitmentParameters<G>>,
+    {
+        let params = value_gen()?.borrow().clone();
+        Ok(BoweHopwoodPedersenCommitmentGadgetParameters {
+            params,
+            _group_g: PhantomData,
+            _engine: PhantomData,
+            _window: PhantomData,
+        })
+    }
+
+    fn alloc_input<F, T, CS: ConstraintSystem<ConstraintF>>(
+        _cs: CS,
+        value_gen: F,
+    ) -> Result<Self, SynthesisError>
+    where
+        F: FnOnce() -> Result<T, SynthesisError>,
+        T: Borrow<BoweHopwoodPedersenCommitmentParameters<G>>,
+    {
+        let params = value_gen()?.borrow().clone();
+        Ok(BoweHopwoodPedersenCommitmentGadgetParameters {
+            params,
+            _group_g: PhantomData,
+            _engine: PhantomData,
+            _window: PhantomData,
+        })
+    }
+}
+
+pub struct FieldBasedEcVrfGadget<G: Group, ConstraintF: Field, GG: GroupGadget<G, ConstraintF>> {
+    _group:        PhantomData<*const G>,
+    _group_gadget: PhantomData<*const GG>,
+    _engine:       PhantomData<ConstraintF>,
+}
+
+impl<G, ConstraintF, GG, W> FieldBasedEcVrfGadget<G, ConstraintF, GG>
+where
+    ConstraintF: Field,
+    G: Group + Hash,
+    GG: GroupGadget<G, ConstraintF> + CompressedGroupGadget<ConstraintF>,
+    W: PedersenWindow,
+{
+    /// Verifies a Schnorr-style EC-VRF proof `(gamma, c, s)` over `message`
+    /// against `public_key`, entirely in-circuit, and checks that `output`
+    /// is the claimed VRF output.
+    ///
+    /// `s` must be supplied least-significant-bit first (the order
+    /// `GroupGadget::mul_bits` consumes), while `c` must be supplied
+    /// most-significant-bit first, matching `recomputed_challenge_bits`
+    /// below against which it is checked; `c` is bit-reversed internally
+    /// before being used as a `mul_bits` exponent.
+    pub fn check_verify_gadget<CS: ConstraintSystem<ConstraintF>>(
+        mut cs: CS,
+        parameters: &BoweHopwoodPedersenCRHGadgetParameters<G, W, ConstraintF, GG>,
+        generator: &GG,
+        public_key: &GG,
+        message: &[UInt8],
+        gamma: &GG,
+        c: &[Boolean],
+        s: &[Boolean],
+        output: &FpGadget<ConstraintF>,
+    ) -> Result<(), SynthesisError> {
+        let h =
+            <BoweHopwoodPedersenCRHGadget<G, ConstraintF, GG> as FixedLengthCRHGadget<BoweHopwoodPedersenCRH<G, W>, ConstraintF>>::check_evaluation_gadget(
+                cs.ns(|| "hash_to_curve"),
+                parameters,
+                message,
+            )?;
+
+        let c_bits_le: Vec<Boolean> = c.iter().rev().cloned().collect();
+        let zero = GG::zero(cs.ns(|| "zero"))?;
+
+        let s_g = generator.mul_bits(cs.ns(|| "s_g"), &zero, s.iter())?;
+        let c_pk = public_key.mul_bits(cs.ns(|| "c_pk"), &zero, c_bits_le.iter())?;
+        let u = s_g.sub(cs.ns(|| "u"), &c_pk)?;
+
+        let s_h = h.mul_bits(cs.ns(|| "s_h"), &zero, s.iter())?;
+        let c_gamma = gamma.mul_bits(cs.ns(|| "c_gamma"), &zero, c_bits_le.iter())?;
+        let v = s_h.sub(cs.ns(|| "v"), &c_gamma)?;
+
+        let mut challenge_input = vec![];
+        challenge_input.extend(public_key.to_bytes(&mut cs.ns(|| "pk_bytes"))?);
+        challenge_input.extend(h.to_bytes(&mut cs.ns(|| "h_bytes"))?);
+        challenge_input.extend(gamma.to_bytes(&mut cs.ns(|| "gamma_bytes"))?);
+        challenge_input.extend(u.to_bytes(&mut cs.ns(|| "u_bytes"))?);
+        challenge_input.extend(v.to_bytes(&mut cs.ns(|| "v_bytes"))?);
+
+        let recomputed_challenge =
+            <BoweHopwoodPedersenCompressedCRHGadget<G, ConstraintF, GG> as FixedLengthCRHGadget<BoweHopwoodPedersenCRH<G, W>, ConstraintF>>::check_evaluation_gadget(
+                cs.ns(|| "recompute_challenge"),
+                parameters,
+                &challenge_input,
+            )?;
+        let recomputed_challenge_bits = recomputed_challenge.to_bits(cs.ns(|| "recomputed_challenge_bits"))?;
+
+        for (i, (supplied_bit, recomputed_bit)) in c.iter().zip(recomputed_challenge_bits.iter()).enumerate() {
+            supplied_bit.enforce_equal(&mut cs.ns(|| format!("challenge_bit_{}", i)), recomputed_bit)?;
+        }
+
+        gamma.x_coordinate().enforce_equal(&mut cs.ns(|| "check_output"), output)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use algebra::fields::sw6::fr::Fr;
@@ -119,15 +471,22 @@ mod test {
         FixedLengthCRHGadget,
         pedersen::PedersenWindow,
         bowe_hopwood::BoweHopwoodPedersenCRH,
-        bowe_hopwood::constraints::BoweHopwoodPedersenCRHGadget,
+        bowe_hopwood::constraints::{
+            BoweHopwoodPedersenCRHGadget, BoweHopwoodPedersenCompressedCRHGadget,
+            BoweHopwoodPedersenCommitmentGadget, BoweHopwoodPedersenCommitmentGadgetParameters,
+            BoweHopwoodPedersenCommitmentParameters, CompressedGroupGadget, FieldBasedEcVrfGadget,
+            MerklePathGadget,
+        },
     };
     use algebra::curves::edwards_sw6::EdwardsProjective as Edwards;
     use r1cs_core::ConstraintSystem;
     use r1cs_std::{
+        bits::boolean::Boolean,
+        fields::fp::FpGadget,
         groups::curves::twisted_edwards::edwards_sw6::EdwardsSWGadget,
         test_constraint_system::TestConstraintSystem, uint8::UInt8, alloc::AllocGadget,
     };
-    use algebra::ProjectiveCurve;
+    use algebra::{Group, PrimeField, ProjectiveCurve, ToBytes};
 
     type TestCRH = BoweHopwoodPedersenCRH<Edwards, Window>;
     type TestCRHGadget = BoweHopwoodPedersenCRHGadget<Edwards, Fr, EdwardsSWGadget>;
@@ -192,4 +551,439 @@ mod test {
         assert_eq!(primitive_result.y, gadget_result.y.value.unwrap());
         assert!(cs.is_satisfied());
     }
+
+    impl CompressedGroupGadget<Fr> for EdwardsSWGadget {
+        fn x_coordinate(&self) -> FpGadget<Fr> {
+            self.x.clone()
+        }
+    }
+
+    #[test]
+    fn crh_primitive_gadget_compressed_test() {
+        let rng = &mut thread_rng();
+        let mut cs = TestConstraintSystem::<Fr>::new();
+
+        let (input, input_bytes) = generate_input(&mut cs, rng);
+
+        let parameters = TestCRH::setup(rng).unwrap();
+        let primitive_result = TestCRH::evaluate_compressed(&parameters, &input).unwrap();
+
+        let gadget_parameters =
+            <BoweHopwoodPedersenCompressedCRHGadget<Edwards, Fr, EdwardsSWGadget> as FixedLengthCRHGadget<TestCRH, Fr>>::ParametersGadget::alloc(
+                &mut cs.ns(|| "compressed_gadget_parameters"),
+                || Ok(&parameters),
+            )
+            .unwrap();
+
+        let gadget_result =
+            <BoweHopwoodPedersenCompressedCRHGadget<Edwards, Fr, EdwardsSWGadget> as FixedLengthCRHGadget<TestCRH, Fr>>::check_evaluation_gadget(
+                &mut cs.ns(|| "compressed_gadget_evaluation"),
+                &gadget_parameters,
+                &input_bytes,
+            )
+            .unwrap();
+
+        assert_eq!(primitive_result, gadget_result.value.unwrap());
+        assert!(cs.is_satisfied());
+    }
+
+    fn two_to_one_native(parameters: &<TestCRH as FixedLengthCRH>::Parameters, left: &Edwards, right: &Edwards) -> Edwards {
+        let mut bytes = vec![];
+        left.into_affine().write(&mut bytes).unwrap();
+        right.into_affine().write(&mut bytes).unwrap();
+        TestCRH::evaluate(parameters, &bytes).unwrap()
+    }
+
+    fn alloc_path<CS: ConstraintSystem<Fr>>(
+        mut cs: CS,
+        path: &[(Edwards, bool)],
+    ) -> MerklePathGadget<Edwards, Fr, EdwardsSWGadget> {
+        let path = path
+            .iter()
+            .enumerate()
+            .map(|(i, (sibling, is_right))| {
+                let sibling = EdwardsSWGadget::alloc(cs.ns(|| format!("sibling_{}", i)), || Ok(*sibling)).unwrap();
+                let is_right = Boolean::alloc(cs.ns(|| format!("is_right_{}", i)), || Ok(*is_right)).unwrap();
+                (sibling, is_right)
+            })
+            .collect();
+        MerklePathGadget::new(path)
+    }
+
+    #[test]
+    fn merkle_path_gadget_test() {
+        let rng = &mut thread_rng();
+        let mut cs = TestConstraintSystem::<Fr>::new();
+
+        let parameters = TestCRH::setup(rng).unwrap();
+
+        let mut leaf = [0u8; 32];
+        rng.fill_bytes(&mut leaf);
+        let mut sibling_leaf = [0u8; 32];
+        rng.fill_bytes(&mut sibling_leaf);
+
+        let leaf_hash = TestCRH::evaluate(&parameters, &leaf).unwrap();
+        let sibling_hash = TestCRH::evaluate(&parameters, &sibling_leaf).unwrap();
+        let root = two_to_one_native(&parameters, &leaf_hash, &sibling_hash);
+
+        let leaf_bytes: Vec<UInt8> = leaf
+            .iter()
+            .enumerate()
+            .map(|(i, byte)| UInt8::alloc(cs.ns(|| format!("leaf_byte_{}", i)), || Ok(*byte)).unwrap())
+            .collect();
+
+        let gadget_parameters =
+            <BoweHopwoodPedersenCRHGadget<Edwards, Fr, EdwardsSWGadget> as FixedLengthCRHGadget<TestCRH, Fr>>::ParametersGadget::alloc(
+                cs.ns(|| "parameters"),
+                || Ok(&parameters),
+            )
+            .unwrap();
+
+        let root_gadget = EdwardsSWGadget::alloc_input(cs.ns(|| "root"), || Ok(root)).unwrap();
+
+        let path = alloc_path(cs.ns(|| "path"), &[(sibling_hash, false)]);
+        path.check_membership(cs.ns(|| "check_membership"), &gadget_parameters, &leaf_bytes, &root_gadget)
+            .unwrap();
+
+        assert!(cs.is_satisfied());
+    }
+
+    #[test]
+    fn merkle_path_gadget_rejects_tampered_path() {
+        let rng = &mut thread_rng();
+        let mut cs = TestConstraintSystem::<Fr>::new();
+
+        let parameters = TestCRH::setup(rng).unwrap();
+
+        let mut leaf = [0u8; 32];
+        rng.fill_bytes(&mut leaf);
+        let mut sibling_leaf = [0u8; 32];
+        rng.fill_bytes(&mut sibling_leaf);
+        let mut wrong_sibling_leaf = [0u8; 32];
+        rng.fill_bytes(&mut wrong_sibling_leaf);
+
+        let leaf_hash = TestCRH::evaluate(&parameters, &leaf).unwrap();
+        let sibling_hash = TestCRH::evaluate(&parameters, &sibling_leaf).unwrap();
+        let wrong_sibling_hash = TestCRH::evaluate(&parameters, &wrong_sibling_leaf).unwrap();
+        let root = two_to_one_native(&parameters, &leaf_hash, &sibling_hash);
+
+        let leaf_bytes: Vec<UInt8> = leaf
+            .iter()
+            .enumerate()
+            .map(|(i, byte)| UInt8::alloc(cs.ns(|| format!("leaf_byte_{}", i)), || Ok(*byte)).unwrap())
+            .collect();
+
+        let gadget_parameters =
+            <BoweHopwoodPedersenCRHGadget<Edwards, Fr, EdwardsSWGadget> as FixedLengthCRHGadget<TestCRH, Fr>>::ParametersGadget::alloc(
+                cs.ns(|| "parameters"),
+                || Ok(&parameters),
+            )
+            .unwrap();
+
+        let root_gadget = EdwardsSWGadget::alloc_input(cs.ns(|| "root"), || Ok(root)).unwrap();
+
+        let path = alloc_path(cs.ns(|| "path"), &[(wrong_sibling_hash, false)]);
+        path.check_membership(cs.ns(|| "check_membership"), &gadget_parameters, &leaf_bytes, &root_gadget)
+            .unwrap();
+
+        assert!(!cs.is_satisfied());
+    }
+
+    /// Computes `scalar * base` where `bytes` is the little-endian byte
+    /// representation of `scalar` (byte 0 holds the least-significant bits).
+    /// This matches `GroupGadget::mul_bits`, which consumes its bit iterator
+    /// least-significant-bit first.
+    fn scalar_mul_from_bytes(base: Edwards, bytes: &[u8]) -> Edwards {
+        let mut result = Edwards::zero();
+        let mut doubled_base = base;
+        for bit in bytes.iter().flat_map(|byte| (0..8).map(move |i| (byte >> i) & 1 == 1)) {
+            if bit {
+                result += &doubled_base;
+            }
+            doubled_base = doubled_base.double();
+        }
+        result
+    }
+
+    #[test]
+    fn commitment_gadget_test() {
+        let rng = &mut thread_rng();
+        let mut cs = TestConstraintSystem::<Fr>::new();
+
+        let (input, input_bytes) = generate_input(&mut cs, rng);
+
+        let mut randomness = [0u8; 32];
+        rng.fill_bytes(&mut randomness);
+        let randomness_bytes: Vec<UInt8> = randomness
+            .iter()
+            .enumerate()
+            .map(|(i, byte)| UInt8::alloc(cs.ns(|| format!("randomness_byte_{}", i)), || Ok(*byte)).unwrap())
+            .collect();
+
+        let crh_parameters = TestCRH::setup(rng).unwrap();
+        let randomness_generator = TestCRH::setup(rng).unwrap().generators[0][0];
+        let commitment_parameters = BoweHopwoodPedersenCommitmentParameters {
+            crh_parameters: crh_parameters.clone(),
+            randomness_generator,
+        };
+
+        let primitive_message_result = TestCRH::evaluate(&crh_parameters, &input).unwrap();
+        let primitive_randomness_result = scalar_mul_from_bytes(randomness_generator, &randomness);
+        let primitive_result = primitive_message_result + &primitive_randomness_result;
+
+        let gadget_parameters = BoweHopwoodPedersenCommitmentGadgetParameters::<Edwards, Window, Fr, EdwardsSWGadget>::alloc(
+            cs.ns(|| "commitment_parameters"),
+            || Ok(&commitment_parameters),
+        )
+        .unwrap();
+
+        let gadget_result = BoweHopwoodPedersenCommitmentGadget::<Edwards, Fr, EdwardsSWGadget>::check_commitment_gadget(
+            cs.ns(|| "commitment_evaluation"),
+            &gadget_parameters,
+            &input_bytes,
+            &randomness_bytes,
+        )
+        .unwrap();
+
+        let primitive_result = primitive_result.into_affine();
+        assert_eq!(primitive_result.x, gadget_result.x.value.unwrap());
+        assert_eq!(primitive_result.y, gadget_result.y.value.unwrap());
+        assert!(cs.is_satisfied());
+    }
+
+    #[test]
+    fn crh_gadget_personalization_test() {
+        let rng = &mut thread_rng();
+        let mut cs = TestConstraintSystem::<Fr>::new();
+
+        let personalization = [0xabu8, 0xcd, 0xef, 0x01];
+        let mut input = [0u8; 32];
+        rng.fill_bytes(&mut input);
+
+        let input_bytes: Vec<UInt8> = input
+            .iter()
+            .enumerate()
+            .map(|(i, byte)| UInt8::alloc(cs.ns(|| format!("input_byte_{}", i)), || Ok(*byte)).unwrap())
+            .collect();
+
+        let parameters = TestCRH::setup(rng).unwrap();
+        let primitive_result = TestCRH::evaluate_with_personalization(&parameters, &personalization, &input)
+            .unwrap()
+            .into_affine();
+
+        let gadget_parameters =
+            <TestCRHGadget as FixedLengthCRHGadget<TestCRH, Fr>>::ParametersGadget::alloc(
+                &mut cs.ns(|| "gadget_parameters"),
+                || Ok(&parameters),
+            )
+            .unwrap();
+
+        let gadget_result = TestCRHGadget::check_evaluation_gadget_with_personalization(
+            cs.ns(|| "gadget_evaluation"),
+            &gadget_parameters,
+            &personalization,
+            &input_bytes,
+        )
+        .unwrap();
+
+        assert_eq!(primitive_result.x, gadget_result.x.value.unwrap());
+        assert_eq!(primitive_result.y, gadget_result.y.value.unwrap());
+        assert!(cs.is_satisfied());
+    }
+
+    #[test]
+    fn crh_gadget_personalization_rejects_oversized_input() {
+        let rng = &mut thread_rng();
+        let mut cs = TestConstraintSystem::<Fr>::new();
+
+        let (_, input_bytes) = generate_input(&mut cs, rng);
+        let parameters = TestCRH::setup(rng).unwrap();
+
+        let gadget_parameters =
+            <TestCRHGadget as FixedLengthCRHGadget<TestCRH, Fr>>::ParametersGadget::alloc(
+                &mut cs.ns(|| "gadget_parameters"),
+                || Ok(&parameters),
+            )
+            .unwrap();
+
+        let result = TestCRHGadget::check_evaluation_gadget_with_personalization(
+            cs.ns(|| "gadget_evaluation"),
+            &gadget_parameters,
+            &[0xab, 0xcd],
+            &input_bytes,
+        );
+
+        assert!(result.is_err());
+    }
+
+    /// Wide enough to hash the five affine points of the VRF challenge input.
+    #[derive(Clone, PartialEq, Eq, Hash)]
+    pub(super) struct VrfWindow;
+
+    impl PedersenWindow for VrfWindow {
+        const WINDOW_SIZE: usize = 90;
+        const NUM_WINDOWS: usize = 16;
+    }
+
+    type VrfCRH = BoweHopwoodPedersenCRH<Edwards, VrfWindow>;
+
+    fn leading_u32_be(bits: &[bool]) -> u32 {
+        bits.iter().take(32).fold(0u32, |acc, &b| (acc << 1) | (b as u32))
+    }
+
+    fn alloc_bits_be<CS: ConstraintSystem<Fr>>(mut cs: CS, name: &str, bytes: &[u8]) -> Vec<Boolean> {
+        bytes
+            .iter()
+            .enumerate()
+            .flat_map(|(byte_i, byte)| {
+                (0..8).rev().map(move |i| {
+                    Boolean::alloc(cs.ns(|| format!("{}_{}_{}", name, byte_i, i)), || Ok((byte >> i) & 1 == 1)).unwrap()
+                })
+            })
+            .collect()
+    }
+
+    /// Allocates `bytes` (little-endian: byte 0 is least-significant) as
+    /// `Boolean`s ordered least-significant-bit first, matching the order
+    /// `GroupGadget::mul_bits` expects for a scalar-multiplication exponent.
+    fn alloc_bits_le<CS: ConstraintSystem<Fr>>(mut cs: CS, name: &str, bytes: &[u8]) -> Vec<Boolean> {
+        bytes
+            .iter()
+            .enumerate()
+            .flat_map(|(byte_i, byte)| {
+                (0..8).map(move |i| {
+                    Boolean::alloc(cs.ns(|| format!("{}_{}_{}", name, byte_i, i)), || Ok((byte >> i) & 1 == 1)).unwrap()
+                })
+            })
+            .collect()
+    }
+
+    struct VrfProof {
+        public_key: Edwards,
+        gamma:      Edwards,
+        /// Big-endian, matching the bit order of `recomputed_challenge_bits`.
+        c_bytes: [u8; 4],
+        /// Little-endian, matching the bit order `GroupGadget::mul_bits` expects.
+        s_bytes: [u8; 8],
+    }
+
+    fn generate_vrf_proof(parameters: &<VrfCRH as FixedLengthCRH>::Parameters, rng: &mut impl Rng, message: &[u8]) -> VrfProof {
+        let generator = Edwards::prime_subgroup_generator();
+        let x: u32 = rng.gen();
+        let k: u32 = rng.gen();
+
+        let h = VrfCRH::evaluate(parameters, message).unwrap();
+        let public_key = scalar_mul_from_bytes(generator, &x.to_le_bytes());
+        let gamma = scalar_mul_from_bytes(h, &x.to_le_bytes());
+        let u = scalar_mul_from_bytes(generator, &k.to_le_bytes());
+        let v = scalar_mul_from_bytes(h, &k.to_le_bytes());
+
+        let mut challenge_input = vec![];
+        public_key.into_affine().write(&mut challenge_input).unwrap();
+        h.into_affine().write(&mut challenge_input).unwrap();
+        gamma.into_affine().write(&mut challenge_input).unwrap();
+        u.into_affine().write(&mut challenge_input).unwrap();
+        v.into_affine().write(&mut challenge_input).unwrap();
+        let challenge_field = VrfCRH::evaluate(parameters, &challenge_input).unwrap().into_affine().x;
+        // `FpGadget::to_bits` yields only the modulus-width bits, so drop the
+        // representation's leading padding bits before taking the top 32.
+        let challenge_bits = challenge_field.into_repr().to_bits();
+        let c = leading_u32_be(&challenge_bits[challenge_bits.len() - Fr::size_in_bits()..]);
+
+        let s = k as u64 + (c as u64) * (x as u64);
+
+        VrfProof {
+            public_key,
+            gamma,
+            c_bytes: c.to_be_bytes(),
+            s_bytes: s.to_le_bytes(),
+        }
+    }
+
+    #[test]
+    fn ecvrf_gadget_accepts_valid_proof() {
+        let rng = &mut thread_rng();
+        let mut cs = TestConstraintSystem::<Fr>::new();
+
+        let message = b"celo ecvrf test message";
+        let parameters = VrfCRH::setup(rng).unwrap();
+        let proof = generate_vrf_proof(&parameters, rng, message);
+
+        let gadget_parameters =
+            <TestCRHGadget as FixedLengthCRHGadget<VrfCRH, Fr>>::ParametersGadget::alloc(cs.ns(|| "parameters"), || Ok(&parameters))
+                .unwrap();
+        let generator_gadget =
+            EdwardsSWGadget::alloc(cs.ns(|| "generator"), || Ok(Edwards::prime_subgroup_generator())).unwrap();
+        let public_key_gadget = EdwardsSWGadget::alloc(cs.ns(|| "public_key"), || Ok(proof.public_key)).unwrap();
+        let gamma_gadget = EdwardsSWGadget::alloc(cs.ns(|| "gamma"), || Ok(proof.gamma)).unwrap();
+        let output_gadget = FpGadget::alloc(cs.ns(|| "output"), || Ok(proof.gamma.into_affine().x)).unwrap();
+
+        let message_bytes: Vec<UInt8> = message
+            .iter()
+            .enumerate()
+            .map(|(i, byte)| UInt8::alloc(cs.ns(|| format!("message_byte_{}", i)), || Ok(*byte)).unwrap())
+            .collect();
+        let c_bits = alloc_bits_be(cs.ns(|| "c"), "c", &proof.c_bytes);
+        let s_bits = alloc_bits_le(cs.ns(|| "s"), "s", &proof.s_bytes);
+
+        FieldBasedEcVrfGadget::<Edwards, Fr, EdwardsSWGadget>::check_verify_gadget(
+            cs.ns(|| "verify"),
+            &gadget_parameters,
+            &generator_gadget,
+            &public_key_gadget,
+            &message_bytes,
+            &gamma_gadget,
+            &c_bits,
+            &s_bits,
+            &output_gadget,
+        )
+        .unwrap();
+
+        assert!(cs.is_satisfied());
+    }
+
+    #[test]
+    fn ecvrf_gadget_rejects_forged_proof() {
+        let rng = &mut thread_rng();
+        let mut cs = TestConstraintSystem::<Fr>::new();
+
+        let message = b"celo ecvrf test message";
+        let parameters = VrfCRH::setup(rng).unwrap();
+        let proof = generate_vrf_proof(&parameters, rng, message);
+
+        let mut forged_s_bytes = proof.s_bytes;
+        forged_s_bytes[7] ^= 1;
+
+        let gadget_parameters =
+            <TestCRHGadget as FixedLengthCRHGadget<VrfCRH, Fr>>::ParametersGadget::alloc(cs.ns(|| "parameters"), || Ok(&parameters))
+                .unwrap();
+        let generator_gadget =
+            EdwardsSWGadget::alloc(cs.ns(|| "generator"), || Ok(Edwards::prime_subgroup_generator())).unwrap();
+        let public_key_gadget = EdwardsSWGadget::alloc(cs.ns(|| "public_key"), || Ok(proof.public_key)).unwrap();
+        let gamma_gadget = EdwardsSWGadget::alloc(cs.ns(|| "gamma"), || Ok(proof.gamma)).unwrap();
+        let output_gadget = FpGadget::alloc(cs.ns(|| "output"), || Ok(proof.gamma.into_affine().x)).unwrap();
+
+        let message_bytes: Vec<UInt8> = message
+            .iter()
+            .enumerate()
+            .map(|(i, byte)| UInt8::alloc(cs.ns(|| format!("message_byte_{}", i)), || Ok(*byte)).unwrap())
+            .collect();
+        let c_bits = alloc_bits_be(cs.ns(|| "c"), "c", &proof.c_bytes);
+        let s_bits = alloc_bits_le(cs.ns(|| "s"), "s", &forged_s_bytes);
+
+        FieldBasedEcVrfGadget::<Edwards, Fr, EdwardsSWGadget>::check_verify_gadget(
+            cs.ns(|| "verify"),
+            &gadget_parameters,
+            &generator_gadget,
+            &public_key_gadget,
+            &message_bytes,
+            &gamma_gadget,
+            &c_bits,
+            &s_bits,
+            &output_gadget,
+        )
+        .unwrap();
+
+        assert!(!cs.is_satisfied());
+    }
 }